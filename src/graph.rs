@@ -0,0 +1,173 @@
+//! A small directed-graph abstraction, kept separate from `Value` so that traversal algorithms
+//! (SCC detection, cycle enumeration) can be reused for any recursive structure a caller defines,
+//! not just the `Rc` cell graph underneath `Value`.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A directed graph over some notion of node, with no further capability assumed
+pub trait DirectedGraph {
+    /// The type identifying a node in the graph
+    type Node;
+}
+
+/// A [`DirectedGraph`] that knows how many distinct nodes it has
+pub trait WithNumNodes: DirectedGraph {
+    /// The number of distinct nodes in the graph
+    fn num_nodes(&self) -> usize;
+}
+
+/// A [`DirectedGraph`] that can enumerate the direct successors of a node
+pub trait WithSuccessors: DirectedGraph {
+    /// The nodes reachable from `node` by a single edge
+    fn successors(&self, node: &Self::Node) -> impl Iterator<Item = Self::Node>;
+}
+
+/// Tarjan's algorithm, generic over any [`WithSuccessors`] graph
+///
+/// Returns the strongly-connected components reachable from `roots`, in the order they finish.
+/// A component of size greater than one, or a singleton with a self-edge, is a genuine cycle.
+pub fn strongly_connected_components<G>(graph: &G, roots: impl IntoIterator<Item = G::Node>) -> Vec<Vec<G::Node>>
+where
+    G: WithSuccessors + WithNumNodes,
+    G::Node: Clone + Eq + Hash,
+{
+    let mut tarjan = Tarjan::with_capacity(graph.num_nodes());
+    for root in roots {
+        if !tarjan.index.contains_key(&root) {
+            tarjan.strongconnect(graph, root);
+        }
+    }
+    tarjan.sccs
+}
+
+/// State for Tarjan's strongly-connected-components algorithm, generic over the node type
+struct Tarjan<Node> {
+    /// Next discovery index to hand out
+    next_index: usize,
+    /// Discovery index of each visited node
+    index: HashMap<Node, usize>,
+    /// Lowest discovery index reachable from each node
+    lowlink: HashMap<Node, usize>,
+    /// Nodes currently on the DFS stack
+    on_stack: HashMap<Node, bool>,
+    /// The DFS stack itself, holding nodes in visitation order
+    stack: Vec<Node>,
+    /// Completed strongly-connected components, in the order they finished
+    sccs: Vec<Vec<Node>>,
+}
+
+impl<Node: Clone + Eq + Hash> Tarjan<Node> {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            next_index: 0,
+            index: HashMap::with_capacity(capacity),
+            lowlink: HashMap::with_capacity(capacity),
+            on_stack: HashMap::with_capacity(capacity),
+            stack: Vec::new(),
+            sccs: Vec::new(),
+        }
+    }
+
+    fn strongconnect<G>(&mut self, graph: &G, node: Node)
+    where
+        G: WithSuccessors<Node = Node>,
+    {
+        self.index.insert(node.clone(), self.next_index);
+        self.lowlink.insert(node.clone(), self.next_index);
+        self.next_index += 1;
+        self.stack.push(node.clone());
+        self.on_stack.insert(node.clone(), true);
+
+        for succ in graph.successors(&node) {
+            if !self.index.contains_key(&succ) {
+                self.strongconnect(graph, succ.clone());
+                let succ_low = self.lowlink[&succ];
+                let low = self.lowlink.get_mut(&node).unwrap();
+                *low = (*low).min(succ_low);
+            } else if self.on_stack.get(&succ).copied().unwrap_or(false) {
+                let succ_index = self.index[&succ];
+                let low = self.lowlink.get_mut(&node).unwrap();
+                *low = (*low).min(succ_index);
+            }
+        }
+
+        if self.lowlink[&node] == self.index[&node] {
+            let mut component = Vec::new();
+            loop {
+                let popped = self.stack.pop().expect("node pushed before strongconnect");
+                self.on_stack.insert(popped.clone(), false);
+                let is_start = popped == node;
+                component.push(popped);
+                if is_start {
+                    break;
+                }
+            }
+            self.sccs.push(component);
+        }
+    }
+}
+
+/// Enumerate concrete cycle paths through a [`WithSuccessors`] graph, depth-first from `roots`
+///
+/// `label` assigns a stable, human-readable name to each node the first time it is seen; the
+/// returned cycles are sequences of labels from the repeated node back to itself.
+pub fn enumerate_cycles<G>(
+    graph: &G,
+    roots: impl IntoIterator<Item = G::Node>,
+    mut label: impl FnMut(&G::Node) -> String,
+) -> Vec<Vec<String>>
+where
+    G: WithSuccessors,
+    G::Node: Clone + Eq + Hash,
+{
+    let mut path: Vec<String> = Vec::new();
+    let mut path_positions: HashMap<G::Node, usize> = HashMap::new();
+    let mut visited: std::collections::HashSet<G::Node> = std::collections::HashSet::new();
+    let mut cycles = Vec::new();
+
+    fn walk<G>(
+        graph: &G,
+        node: G::Node,
+        path: &mut Vec<String>,
+        path_positions: &mut HashMap<G::Node, usize>,
+        visited: &mut std::collections::HashSet<G::Node>,
+        label: &mut impl FnMut(&G::Node) -> String,
+        cycles: &mut Vec<Vec<String>>,
+    ) where
+        G: WithSuccessors,
+        G::Node: Clone + Eq + Hash,
+    {
+        let name = label(&node);
+
+        // A node already on the path is a back-edge: slice out the cycle and stop
+        if let Some(&start) = path_positions.get(&node) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(name);
+            cycles.push(cycle);
+            return;
+        }
+
+        // A node we've already fully explored elsewhere; don't reprocess its subgraph
+        if visited.contains(&node) {
+            return;
+        }
+
+        path.push(name);
+        path_positions.insert(node.clone(), path.len() - 1);
+
+        for succ in graph.successors(&node) {
+            walk(graph, succ, path, path_positions, visited, label, cycles);
+        }
+
+        path_positions.remove(&node);
+        path.pop();
+        visited.insert(node);
+    }
+
+    for root in roots {
+        walk(graph, root, &mut path, &mut path_positions, &mut visited, &mut label, &mut cycles);
+    }
+
+    cycles
+}
@@ -1,9 +1,13 @@
 use std::{
     cell::RefCell,
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     rc::Rc,
 };
 
+mod graph;
+
+use graph::{DirectedGraph, WithNumNodes, WithSuccessors};
+
 /// An example of a recursive data structure
 #[derive(Clone)]
 enum Value {
@@ -38,10 +42,308 @@ impl Value {
     }
 }
 
+/// Identifies a graph node by the allocation pointer of its `Rc<RefCell<Option<Value>>>` cell
+type NodeId = *const RefCell<Option<Value>>;
+
+impl Value {
+    /// Find the strongly-connected components of the `Rc` cell graph reachable from this value
+    ///
+    /// Each returned component is a list of cell pointers; components of size greater than one,
+    /// or singletons with a self-edge, are genuine cycles.
+    fn strongly_connected_components(&self) -> Vec<Vec<NodeId>> {
+        let graph = ValueGraph::discover(&[self]);
+        graph::strongly_connected_components(&graph, graph.cells.clone())
+            .into_iter()
+            .map(|component| component.into_iter().map(|cell| cell.id()).collect())
+            .collect()
+    }
+
+    /// Collect the `Rc` cells directly reachable from a value (one hop, not recursing through them)
+    fn reachable_rc_cells(value: &Value) -> Vec<Rc<RefCell<Option<Value>>>> {
+        match value {
+            Value::Int(_) => Vec::new(),
+            Value::Rc(cell) => vec![Rc::clone(cell)],
+            Value::List(xs) => xs.iter().flat_map(Self::reachable_rc_cells).collect(),
+        }
+    }
+
+    /// The `Rc` cells directly reachable from a cell's resolved content, or none if uninitialized
+    fn successors_of_cell(cell: &Rc<RefCell<Option<Value>>>) -> Vec<Rc<RefCell<Option<Value>>>> {
+        match &*cell.borrow() {
+            None => Vec::new(),
+            Some(value) => Self::reachable_rc_cells(value),
+        }
+    }
+}
+
+/// A handle to an `Rc<RefCell<Option<Value>>>` cell, usable as a graph node
+///
+/// `Value` has no `Eq`/`Hash` impl of its own, but the allocation pointer is exactly the node
+/// identity the traversal algorithms in [`graph`] care about, so this wrapper supplies it.
+#[derive(Clone)]
+struct RcCell(Rc<RefCell<Option<Value>>>);
+
+impl RcCell {
+    /// The allocation pointer identifying this cell
+    fn id(&self) -> NodeId {
+        Rc::as_ptr(&self.0)
+    }
+}
+
+impl PartialEq for RcCell {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id()
+    }
+}
+
+impl Eq for RcCell {}
+
+impl std::hash::Hash for RcCell {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id().hash(state);
+    }
+}
+
+/// The `Rc` cell graph reachable from a set of roots, the concrete [`DirectedGraph`] the cycle
+/// detection algorithms in [`graph`] run over
+///
+/// Every reachable cell is discovered up front, so the node count is known before any algorithm
+/// runs over it.
+struct ValueGraph {
+    /// Every `Rc` cell reachable from the roots, deduped by allocation pointer
+    cells: Vec<RcCell>,
+}
+
+impl ValueGraph {
+    /// Discover every `Rc` cell reachable from `roots`
+    fn discover(roots: &[&Value]) -> Self {
+        let mut seen = HashSet::new();
+        let mut cells = Vec::new();
+        let mut stack: Vec<Rc<RefCell<Option<Value>>>> = roots
+            .iter()
+            .copied()
+            .flat_map(Value::reachable_rc_cells)
+            .collect();
+        while let Some(cell) = stack.pop() {
+            if !seen.insert(Rc::as_ptr(&cell)) {
+                continue;
+            }
+            stack.extend(Value::successors_of_cell(&cell));
+            cells.push(RcCell(cell));
+        }
+        Self { cells }
+    }
+}
+
+impl DirectedGraph for ValueGraph {
+    type Node = RcCell;
+}
+
+impl WithNumNodes for ValueGraph {
+    fn num_nodes(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+impl WithSuccessors for ValueGraph {
+    fn successors(&self, node: &RcCell) -> impl Iterator<Item = RcCell> {
+        Value::successors_of_cell(&node.0).into_iter().map(RcCell)
+    }
+}
+
+impl Value {
+    /// Reclaim `Rc<RefCell>` cycles reachable from `roots` using Bacon-Rajan trial deletion
+    ///
+    /// Cells that lie on a cycle and have no reference from outside the traced subgraph (the
+    /// roots' own references do not count) are cleared, breaking the cycle and letting plain
+    /// reference counting free them; cells not on any cycle are left as they are. Returns the
+    /// number of cells reclaimed.
+    fn collect_garbage(roots: &[&Value]) -> usize {
+        // Discover every reachable cell up front; the graph owns one clone of each
+        let graph = ValueGraph::discover(roots);
+
+        // "Mark gray": seed each cell's shadow count from its real strong count, discounting
+        // both the scratch clone held in `graph` and the direct reference(s) `roots` itself
+        // provides. Neither is an edge from another candidate cell, but neither should anchor a
+        // cell alive either: both exist purely to let this pass reach the cell, not because
+        // something outside the traced subgraph still needs it. Then decrement once more per
+        // internal edge into the cell.
+        let mut shadow: HashMap<NodeId, isize> = graph
+            .cells
+            .iter()
+            .map(|cell| (cell.id(), Rc::strong_count(&cell.0) as isize - 1))
+            .collect();
+        for root in roots.iter().copied() {
+            for cell in Self::reachable_rc_cells(root) {
+                if let Some(count) = shadow.get_mut(&Rc::as_ptr(&cell)) {
+                    *count -= 1;
+                }
+            }
+        }
+        for cell in &graph.cells {
+            for succ in graph.successors(cell) {
+                if let Some(count) = shadow.get_mut(&succ.id()) {
+                    *count -= 1;
+                }
+            }
+        }
+
+        // "Scan": a positive shadow count means an external reference keeps the cell (and
+        // everything reachable from it) alive; everything else is left white
+        let mut black = HashSet::new();
+        for cell in &graph.cells {
+            if shadow[&cell.id()] > 0 {
+                Self::mark_black(&cell.0, &mut black);
+            }
+        }
+
+        // A white cell is only collectable if it actually participates in a cycle (an SCC of
+        // size greater than one, or a singleton with a self-edge); a white cell that is merely
+        // a plain, non-cyclic chain hanging off a root is live data reachable through that root,
+        // not garbage, and clearing it would corrupt the root.
+        let cyclic: HashSet<NodeId> = graph::strongly_connected_components(&graph, graph.cells.clone())
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1
+                    || component.iter().any(|cell| {
+                        graph.successors(cell).any(|succ| succ.id() == cell.id())
+                    })
+            })
+            .flat_map(|component| component.into_iter().map(|cell| cell.id()))
+            .collect();
+
+        // "Collect": clear the white cells that form genuine cycles, dropping their internal
+        // references and breaking the cycle
+        let mut collected = 0;
+        for cell in &graph.cells {
+            if !black.contains(&cell.id()) && cyclic.contains(&cell.id()) {
+                *cell.0.borrow_mut() = None;
+                collected += 1;
+            }
+        }
+        collected
+    }
+
+    /// Mark `cell` and everything reachable from it "black" (externally live), restoring it
+    fn mark_black(cell: &Rc<RefCell<Option<Value>>>, black: &mut HashSet<NodeId>) {
+        if !black.insert(Rc::as_ptr(cell)) {
+            return;
+        }
+        for succ in Self::successors_of_cell(cell) {
+            Self::mark_black(&succ, black);
+        }
+    }
+}
+
+impl Value {
+    /// Enumerate concrete cycle paths through the value reachable from this value, e.g.
+    /// `"Cycle 1: rc#0 -> list -> rc#0"`
+    ///
+    /// Each cycle is rendered as `"Cycle N: <label> -> <label> -> ... -> <label>"`, where the
+    /// first and last label are the same `Rc` cell that closes the cycle, and a `list` label
+    /// marks each `List` layer the path passes through. A cell reachable by more than one
+    /// sibling edge can otherwise yield the same cycle path several times over, so identical
+    /// cycles are de-duplicated before numbering.
+    fn describe_cycles(&self) -> String {
+        let Some(root) = TreeNode::from_value(self) else {
+            return String::new();
+        };
+
+        // Assign each cell a stable `rc#N` label the first time `enumerate_cycles` visits it
+        let mut labels: HashMap<NodeId, String> = HashMap::new();
+        let cycles = graph::enumerate_cycles(&ValueTreeGraph, [root], |node| match node {
+            TreeNode::Cell(cell) => {
+                let next = labels.len();
+                labels.entry(cell.id()).or_insert_with(|| format!("rc#{}", next)).clone()
+            }
+            TreeNode::List(_) => "list".to_string(),
+        });
+
+        let mut seen = HashSet::new();
+        cycles
+            .into_iter()
+            .filter(|cycle| seen.insert(cycle.clone()))
+            .enumerate()
+            .map(|(i, cycle)| format!("Cycle {}: {}", i + 1, cycle.join(" -> ")))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// A node in the full `Value` tree walk that backs [`Value::describe_cycles`]: either an `Rc`
+/// cell or a structural `List` layer
+///
+/// Unlike [`ValueGraph`], which flattens lists away to expose only the `Rc` cell topology, this
+/// graph walks the tree as printed, so cycle paths can show the `list` hops a cycle passes
+/// through. `List` nodes have no stable identity of their own: each occurrence is unique and
+/// never revisited, matching how a `List` layer is just a presentation detail, not a cyclable
+/// entity.
+#[derive(Clone)]
+enum TreeNode {
+    Cell(RcCell),
+    List(Vec<Value>),
+}
+
+impl TreeNode {
+    /// The node a `Value` contributes to the tree walk, or `None` for a leaf `Int`
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Int(_) => None,
+            Value::Rc(cell) => Some(TreeNode::Cell(RcCell(Rc::clone(cell)))),
+            Value::List(xs) => Some(TreeNode::List(xs.clone())),
+        }
+    }
+}
+
+impl PartialEq for TreeNode {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (TreeNode::Cell(a), TreeNode::Cell(b)) => a == b,
+            // `List` nodes are never identified with one another, even themselves, so they are
+            // never treated as already-visited or as closing a cycle
+            _ => false,
+        }
+    }
+}
+
+impl Eq for TreeNode {}
+
+impl std::hash::Hash for TreeNode {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        if let TreeNode::Cell(cell) = self {
+            cell.hash(state);
+        }
+    }
+}
+
+/// The full `Value` tree, traversed without flattening away `List` layers
+struct ValueTreeGraph;
+
+impl DirectedGraph for ValueTreeGraph {
+    type Node = TreeNode;
+}
+
+impl WithSuccessors for ValueTreeGraph {
+    fn successors(&self, node: &TreeNode) -> impl Iterator<Item = TreeNode> {
+        let children: Vec<TreeNode> = match node {
+            TreeNode::Cell(cell) => match &*cell.0.borrow() {
+                None => Vec::new(),
+                Some(inner) => TreeNode::from_value(inner).into_iter().collect(),
+            },
+            TreeNode::List(xs) => xs.iter().filter_map(TreeNode::from_value).collect(),
+        };
+        children.into_iter()
+    }
+}
+
 impl std::fmt::Debug for Value {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // Create an instance of the helper data structure
-        let mut fmt1 = ValueFormatter::default();
+        // Create an instance of the helper data structure, seeded with which Rc cells are shared
+        // or cyclic so they get a reusable back-reference label
+        let mut fmt1 = ValueFormatter {
+            shared: ValueFormatter::find_shared(self),
+            ..ValueFormatter::default()
+        };
 
         // Populate the data structure with string snippets
         fmt1.visit(self);
@@ -54,10 +356,27 @@ impl std::fmt::Debug for Value {
     }
 }
 
+/// A pending unit of work for `ValueFormatter`'s iterative traversal
+///
+/// `Enter` holds an owned `Value` rather than a borrow so that, when it comes from inside an
+/// `Rc` cell, it can be cloned out of the cell's `Ref` guard on the spot instead of carrying
+/// that guard's borrow across later loop iterations.
+enum WorkItem {
+    /// Format a value, pushing whatever further work it implies
+    Enter(Value),
+    /// Append a literal chunk directly (list separators and closing brackets)
+    EmitLiteral(&'static str),
+}
+
 /// Helper data structure
 #[derive(Debug, Default)]
 struct ValueFormatter {
-    visited: HashSet<*const Value>,
+    /// `Rc` cells already printed, identified by their heap allocation pointer
+    visited: HashSet<NodeId>,
+    /// `Rc` cells reachable more than once (true sharing, including self-cycles)
+    shared: HashSet<NodeId>,
+    /// Back-reference label assigned to each shared `Rc` cell, in first-seen order
+    labels: HashMap<NodeId, usize>,
     chunks: Vec<String>,
 }
 
@@ -73,42 +392,89 @@ impl ValueFormatter {
         self.chunks.join("")
     }
 
-    /// Main recursive function
+    /// Find the `Rc` cells that are reachable more than once from `value`
+    ///
+    /// Driven by an explicit worklist, like [`Self::visit`], so this pre-pass can't overflow the
+    /// native call stack on deep input either.
+    fn find_shared(value: &Value) -> HashSet<NodeId> {
+        let mut seen = HashSet::new();
+        let mut shared = HashSet::new();
+        let mut worklist = vec![value.clone()];
+
+        while let Some(value) = worklist.pop() {
+            match value {
+                Value::Int(_) => {}
+                Value::List(xs) => worklist.extend(xs),
+                Value::Rc(cell) => {
+                    let id = Rc::as_ptr(&cell);
+                    if !seen.insert(id) {
+                        // Seen before: the cell is shared (or, if on the active path, cyclic)
+                        shared.insert(id);
+                        continue;
+                    }
+                    if let Some(inner) = &*cell.borrow() {
+                        worklist.push(inner.clone());
+                    }
+                }
+            }
+        }
+
+        shared
+    }
+
+    /// Iterative traversal driven by an explicit worklist, so deep/cyclic `Value`s can't
+    /// overflow the native call stack the way direct recursion would
     fn visit(&mut self, x: &Value) {
-        let ptr = x as *const Value;
-
-        // Check if the node has already been visited
-        match self.visited.contains(&ptr) {
-            // Self-references are represented by "*"
-            true => {self.add("*");}
-
-            // Non self-reference
-            false => {
-                // Insert the node into visited
-                self.visited.insert(ptr);
-                match x {
+        let mut worklist = vec![WorkItem::Enter(x.clone())];
+
+        while let Some(item) = worklist.pop() {
+            match item {
+                // Separators and closing brackets queued by a prior `List` item
+                WorkItem::EmitLiteral(s) => self.add(s),
+
+                WorkItem::Enter(value) => match value {
                     // Format an Int value
                     Value::Int(x) => {self.add(x);}
 
-                    // Format an Rc value, branching on whether it is initialized or not
-                    Value::Rc(x) => match &*x.borrow() {
-                        None => self.add("uninit"),
-                        Some(x) => self.visit(x),
+                    // Format an Rc value, keyed on its allocation pointer rather than a local address
+                    Value::Rc(cell) => {
+                        let id = Rc::as_ptr(&cell);
+
+                        // Already printed: emit just the back-reference label
+                        if self.visited.contains(&id) {
+                            let label = self.labels[&id];
+                            self.add(format!("#{}", label));
+                            continue;
+                        }
+                        self.visited.insert(id);
+
+                        // First time printing a shared/cyclic cell: bind it to a fresh label
+                        if self.shared.contains(&id) {
+                            let label = self.labels.len();
+                            self.labels.insert(id, label);
+                            self.add(format!("#{} = ", label));
+                        }
+
+                        // Clone the resolved content out of the `Ref` guard right here, rather
+                        // than carrying the guard's borrow into a later pop of the worklist
+                        match &*cell.borrow() {
+                            None => self.add("uninit"),
+                            Some(inner) => worklist.push(WorkItem::Enter(inner.clone())),
+                        }
                     }
 
-                    // Format a List value
+                    // Format a List value; push children in reverse so they pop in order
                     Value::List(xs) => {
                         self.add("[");
-                        if let Some(x) = xs.first() {
-                            self.visit(x);
-                            for x in &xs[1..] {
-                                self.add(", ");
-                                self.visit(x);
+                        worklist.push(WorkItem::EmitLiteral("]"));
+                        for (i, x) in xs.iter().enumerate().rev() {
+                            worklist.push(WorkItem::Enter(x.clone()));
+                            if i > 0 {
+                                worklist.push(WorkItem::EmitLiteral(", "));
                             }
                         }
-                        self.add("]");
                     }
-                }
+                },
             }
         }
     }
@@ -134,6 +500,87 @@ fn main() {
     // Resolve the reference, creating a cyclic data structure
     x.resolve(y);
 
-    // [1, 2, 3, *, *, *]
+    // #0 = [1, 2, 3, #0, #0, #0]
     dbg!(&x);
+
+    // One strongly-connected component: the self-cycle through `x`'s own cell
+    dbg!(x.strongly_connected_components().len());
+
+    // Cycle 1: rc#0 -> list -> rc#0
+    println!("{}", x.describe_cycles());
+
+    // A second, detached cycle reachable only via `a` once `b`'s own handle is dropped
+    let mut a = rc();
+    let mut b = rc();
+    b.resolve(list(vec![a.clone()]));
+    a.resolve(list(vec![b.clone()]));
+    drop(b);
+
+    // Reclaims both cells of the detached a <-> b cycle
+    dbg!(Value::collect_garbage(&[&a]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collect_garbage_reclaims_a_detached_self_cycle() {
+        let mut a = rc();
+        a.resolve(list(vec![a.clone()]));
+
+        let collected = Value::collect_garbage(&[&a]);
+        assert_eq!(collected, 1);
+
+        let Value::Rc(cell) = &a else { unreachable!() };
+        assert!(cell.borrow().is_none(), "self-cycle should have been cleared");
+    }
+
+    #[test]
+    fn collect_garbage_reclaims_a_detached_two_node_cycle() {
+        let mut a = rc();
+        let mut b = rc();
+        b.resolve(list(vec![a.clone()]));
+        a.resolve(list(vec![b.clone()]));
+        drop(b);
+
+        let collected = Value::collect_garbage(&[&a]);
+        assert_eq!(collected, 2);
+
+        let Value::Rc(cell) = &a else { unreachable!() };
+        assert!(cell.borrow().is_none(), "two-node cycle should have been cleared");
+    }
+
+    #[test]
+    fn collect_garbage_keeps_a_cell_with_a_genuine_external_reference() {
+        let mut a = rc();
+        a.resolve(list(vec![a.clone()]));
+
+        // A handle outside the traced roots keeps the cell alive
+        let keep = a.clone();
+
+        let collected = Value::collect_garbage(&[&a]);
+        assert_eq!(collected, 0);
+
+        let Value::Rc(cell) = &keep else { unreachable!() };
+        assert!(cell.borrow().is_some(), "externally-referenced cell must survive");
+    }
+
+    #[test]
+    fn collect_garbage_keeps_a_non_cyclic_cell_reachable_through_a_root() {
+        let mut c = rc();
+        c.resolve(int(5));
+        let a = list(vec![c.clone()]);
+        drop(c);
+
+        let collected = Value::collect_garbage(&[&a]);
+        assert_eq!(collected, 0, "a's own (non-cyclic) data must not be reclaimed");
+
+        let Value::List(xs) = &a else { unreachable!() };
+        let Value::Rc(cell) = &xs[0] else { unreachable!() };
+        assert!(
+            matches!(&*cell.borrow(), Some(Value::Int(5))),
+            "c's cell must still hold its data"
+        );
+    }
 }